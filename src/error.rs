@@ -0,0 +1,28 @@
+use crate::varint::VarIntError;
+
+/// Result type used throughout the crate's fallible decoders.
+pub type Res<T> = Result<T, Error>;
+
+/// Errors produced while decoding a QUIC packet.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The datagram ended before a field could be fully read.
+    UnexpectedEnd,
+
+    /// A field held a value that is invalid for this version of QUIC, such as a
+    /// connection ID longer than the 20-byte version 1 limit.
+    InvalidPacket,
+
+    /// A variable-length integer could not be decoded.
+    VarInt(VarIntError),
+
+    /// A Retry packet's integrity tag did not match the one computed over the
+    /// reconstructed pseudo-packet; the Retry is forged and must be dropped.
+    RetryIntegrity,
+}
+
+impl From<VarIntError> for Error {
+    fn from(e: VarIntError) -> Self {
+        Self::VarInt(e)
+    }
+}
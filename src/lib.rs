@@ -0,0 +1,3 @@
+pub mod error;
+pub mod packet;
+pub mod varint;
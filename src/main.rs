@@ -6,8 +6,10 @@ fn main() {
     let (amt, src) = socket.recv_from(&mut buf).unwrap();
     // Redeclare `buf` as slice of the received data and send reverse data back to origin.
     let buf = &mut buf[..amt];
-    let header = LongHeader::from_slice(buf);
-    println!("{:?}", header);
+    match Packet::from_slice(buf) {
+        Ok(packet) => println!("{:?}", packet),
+        Err(err) => eprintln!("failed to decode packet: {:?}", err),
+    }
     buf.reverse();
     socket.send_to(buf, src).unwrap();
 }
@@ -1,4 +1,58 @@
-use core::ops::RangeBounds;
+use aes::cipher::{BlockEncrypt, KeyIvInit, StreamCipher, StreamCipherSeek};
+use aes::Aes128;
+use aes_gcm::aead::{Aead, KeyInit as AeadKeyInit, Payload};
+use aes_gcm::Aes128Gcm;
+use chacha20::ChaCha20;
+use subtle::ConstantTimeEq;
+
+/// A QUIC packet number. The on-the-wire encoding is 1 to 4 bytes, but the
+/// packet-number space is 62 bits, so the decoded value is carried as a `u64`.
+pub type PacketNumber = u64;
+
+/// Offset, in bytes, from the start of the Packet Number field to the start of
+/// the header-protection sample; see Section 5.4.2 of [QUIC-TLS].
+const SAMPLE_OFFSET: usize = 4;
+
+/// Length, in bytes, of the header-protection sample.
+const SAMPLE_SIZE: usize = 16;
+
+/// The header-protection key and cipher used to mask the first byte and packet
+/// number of a QUIC packet. Initial packets derive an AES suite; the cipher is
+/// selected from the negotiated TLS 1.3 cipher suite for later packets.
+pub enum HeaderProtectionKey {
+    /// `AES_128_GCM_SHA256`: the sample is encrypted with a single AES-128-ECB
+    /// block operation to produce the mask. The 32-byte AES-256 header
+    /// protection key of `AES_256_GCM_SHA384` has no variant here.
+    Aes128([u8; 16]),
+
+    /// `CHACHA20_POLY1305_SHA256`: the first four bytes of the sample are the
+    /// block counter and the remaining twelve are the nonce; the mask is the
+    /// keystream produced for five zero bytes.
+    ChaCha20([u8; 32]),
+}
+
+impl HeaderProtectionKey {
+    /// Derive the five-byte header-protection mask from a 16-byte sample.
+    fn mask(&self, sample: &[u8]) -> [u8; 5] {
+        let mut out = [0u8; 5];
+        match self {
+            Self::Aes128(key) => {
+                let cipher = Aes128::new(key.into());
+                let mut block = [0u8; 16];
+                block.copy_from_slice(sample);
+                cipher.encrypt_block((&mut block).into());
+                out.copy_from_slice(&block[..5]);
+            }
+            Self::ChaCha20(key) => {
+                let counter = u32::from_le_bytes([sample[0], sample[1], sample[2], sample[3]]);
+                let mut cipher = ChaCha20::new(key.into(), sample[4..16].into());
+                cipher.seek(counter as u64 * 64);
+                cipher.apply_keystream(&mut out);
+            }
+        }
+        out
+    }
+}
 
 #[allow(non_camel_case_types)]
 pub enum FrameType {
@@ -79,9 +133,252 @@ impl FrameType {
     }
 }
 
-pub struct Frame {
-    pub ftype: FrameType,
-    pub fields: Vec<u8>,
+/// ECN counts carried by an ACK frame of type 0x03.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EcnCounts {
+    pub ect0: u64,
+    pub ect1: u64,
+    pub ce: u64,
+}
+
+/// A decoded QUIC frame. Only the frame types the crate currently interprets
+/// have rich variants; see Section 19 of [QUIC-TRANSPORT].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Frame {
+    Padding,
+    Ping,
+    Ack {
+        /// Largest packet number being acknowledged.
+        largest_acknowledged: u64,
+        /// Acknowledgement delay, in the peer's ACK-delay-exponent units.
+        ack_delay: u64,
+        /// Acknowledged packet-number intervals, in ascending order.
+        ack_ranges: Vec<core::ops::RangeInclusive<u64>>,
+        /// ECN counts, present for ACK frames of type 0x03.
+        ecn: Option<EcnCounts>,
+    },
+    ResetStream {
+        stream_id: u64,
+        application_error_code: u64,
+        final_size: u64,
+    },
+    Crypto {
+        offset: u64,
+        data: Vec<u8>,
+    },
+    Stream {
+        stream_id: u64,
+        offset: u64,
+        fin: bool,
+        data: Vec<u8>,
+    },
+    MaxData {
+        maximum_data: u64,
+    },
+    MaxStreamData {
+        stream_id: u64,
+        maximum_stream_data: u64,
+    },
+    ConnectionClose {
+        error_code: u64,
+        /// The frame type that triggered the error, present only for the
+        /// transport-level variant (type 0x1c).
+        frame_type: Option<u64>,
+        reason_phrase: Vec<u8>,
+    },
+    NewConnectionId {
+        sequence_number: u64,
+        retire_prior_to: u64,
+        connection_id: Vec<u8>,
+        stateless_reset_token: [u8; 16],
+    },
+}
+
+impl Frame {
+    /// Parse every frame in a decrypted packet payload.
+    ///
+    /// Frames are laid out back to back with no length prefix, so each decoder
+    /// consumes exactly its own bytes before the next type byte is read. A
+    /// malformed or truncated frame, or a type this crate does not interpret,
+    /// aborts the walk with an error rather than panicking.
+    pub fn parse_all(payload: &[u8]) -> crate::error::Res<Vec<Frame>> {
+        use crate::error::Error;
+
+        let mut frames = Vec::new();
+        let mut pos = 0;
+        while pos < payload.len() {
+            let ftype = FrameType::from_u8(payload[pos]);
+            let type_byte = payload[pos];
+            pos += 1;
+            let frame = match ftype {
+                FrameType::PADDING => Frame::Padding,
+                FrameType::PING => Frame::Ping,
+                FrameType::ACK(_) => {
+                    let largest_acknowledged = take_varint(payload, &mut pos)?;
+                    let ack_delay = take_varint(payload, &mut pos)?;
+                    let range_count = take_varint(payload, &mut pos)?;
+                    let first_range = take_varint(payload, &mut pos)?;
+
+                    let mut ack_ranges = Vec::new();
+                    // The first range descends from the largest acknowledged.
+                    let mut low = largest_acknowledged
+                        .checked_sub(first_range)
+                        .ok_or(Error::InvalidPacket)?;
+                    ack_ranges.push(low..=largest_acknowledged);
+
+                    for _ in 0..range_count {
+                        let gap = take_varint(payload, &mut pos)?;
+                        let len = take_varint(payload, &mut pos)?;
+                        // Next range's largest is two below the current low,
+                        // minus the gap; see Section 19.3.1.
+                        let next_largest = low
+                            .checked_sub(gap + 2)
+                            .ok_or(Error::InvalidPacket)?;
+                        low = next_largest.checked_sub(len).ok_or(Error::InvalidPacket)?;
+                        ack_ranges.push(low..=next_largest);
+                    }
+                    // Materialize in ascending order for congestion control.
+                    ack_ranges.reverse();
+
+                    let ecn = if type_byte == 0x03 {
+                        Some(EcnCounts {
+                            ect0: take_varint(payload, &mut pos)?,
+                            ect1: take_varint(payload, &mut pos)?,
+                            ce: take_varint(payload, &mut pos)?,
+                        })
+                    } else {
+                        None
+                    };
+
+                    Frame::Ack {
+                        largest_acknowledged,
+                        ack_delay,
+                        ack_ranges,
+                        ecn,
+                    }
+                }
+                FrameType::RESET_STREAM => Frame::ResetStream {
+                    stream_id: take_varint(payload, &mut pos)?,
+                    application_error_code: take_varint(payload, &mut pos)?,
+                    final_size: take_varint(payload, &mut pos)?,
+                },
+                FrameType::CRYPTO => {
+                    let offset = take_varint(payload, &mut pos)?;
+                    let length = take_varint(payload, &mut pos)? as usize;
+                    let data = take_bytes(payload, &mut pos, length)?;
+                    Frame::Crypto { offset, data }
+                }
+                FrameType::STREAM(_) => {
+                    let stream_id = take_varint(payload, &mut pos)?;
+                    let offset = if type_byte & 0x04 != 0 {
+                        take_varint(payload, &mut pos)?
+                    } else {
+                        0
+                    };
+                    let data = if type_byte & 0x02 != 0 {
+                        let length = take_varint(payload, &mut pos)? as usize;
+                        take_bytes(payload, &mut pos, length)?
+                    } else {
+                        // No length: the stream extends to the end of the packet.
+                        let data = payload.get(pos..).ok_or(Error::UnexpectedEnd)?.to_vec();
+                        pos = payload.len();
+                        data
+                    };
+                    Frame::Stream {
+                        stream_id,
+                        offset,
+                        fin: type_byte & 0x01 != 0,
+                        data,
+                    }
+                }
+                FrameType::MAX_DATA => Frame::MaxData {
+                    maximum_data: take_varint(payload, &mut pos)?,
+                },
+                FrameType::MAX_STREAM_DATA => Frame::MaxStreamData {
+                    stream_id: take_varint(payload, &mut pos)?,
+                    maximum_stream_data: take_varint(payload, &mut pos)?,
+                },
+                FrameType::CONNECTION_CLOSE(_) => {
+                    let error_code = take_varint(payload, &mut pos)?;
+                    let frame_type = if type_byte == 0x1c {
+                        Some(take_varint(payload, &mut pos)?)
+                    } else {
+                        None
+                    };
+                    let len = take_varint(payload, &mut pos)? as usize;
+                    let reason_phrase = take_bytes(payload, &mut pos, len)?;
+                    Frame::ConnectionClose {
+                        error_code,
+                        frame_type,
+                        reason_phrase,
+                    }
+                }
+                FrameType::NEW_CONNECTION_ID => {
+                    let sequence_number = take_varint(payload, &mut pos)?;
+                    let retire_prior_to = take_varint(payload, &mut pos)?;
+                    let len = *payload.get(pos).ok_or(Error::UnexpectedEnd)? as usize;
+                    pos += 1;
+                    let connection_id = take_bytes(payload, &mut pos, len)?;
+                    let token = take_bytes(payload, &mut pos, 16)?;
+                    let mut stateless_reset_token = [0u8; 16];
+                    stateless_reset_token.copy_from_slice(&token);
+                    Frame::NewConnectionId {
+                        sequence_number,
+                        retire_prior_to,
+                        connection_id,
+                        stateless_reset_token,
+                    }
+                }
+                // Frame types this crate does not yet interpret carry no length
+                // prefix, so the walk cannot safely skip them.
+                _ => return Err(Error::InvalidPacket),
+            };
+            frames.push(frame);
+        }
+        Ok(frames)
+    }
+}
+
+/// Read a variable-length integer at `*pos`, advancing the cursor past it.
+fn take_varint(buf: &[u8], pos: &mut usize) -> crate::error::Res<u64> {
+    let rest = buf.get(*pos..).ok_or(crate::error::Error::UnexpectedEnd)?;
+    let (v, n) = crate::varint::VarInt::decode(rest)?;
+    *pos += n;
+    Ok(v.value())
+}
+
+/// Copy `len` bytes at `*pos`, advancing the cursor past them.
+fn take_bytes(buf: &[u8], pos: &mut usize, len: usize) -> crate::error::Res<Vec<u8>> {
+    let end = pos.checked_add(len).ok_or(crate::error::Error::UnexpectedEnd)?;
+    let bytes = buf.get(*pos..end).ok_or(crate::error::Error::UnexpectedEnd)?.to_vec();
+    *pos = end;
+    Ok(bytes)
+}
+
+/// The wire value of QUIC version 1 ([RFC9000]).
+pub const VERSION_1: u32 = 0x0000_0001;
+
+/// The wire value of QUIC version 2 ([RFC9369]).
+pub const VERSION_2: u32 = 0x6b33_43cf;
+
+/// A recognized QUIC version. The raw 32-bit value is kept in the header; this
+/// enum classifies it so version-specific field layouts (notably the long
+/// packet-type numbering) can be resolved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Version {
+    Version1,
+    Version2,
+    Unknown(u32),
+}
+
+impl From<u32> for Version {
+    fn from(value: u32) -> Self {
+        match value {
+            VERSION_1 => Self::Version1,
+            VERSION_2 => Self::Version2,
+            value => Self::Unknown(value),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -106,6 +403,20 @@ impl From<u8> for LongPacketType {
     }
 }
 
+impl LongPacketType {
+    /// Resolve the logical packet type from the two type bits of byte 0, taking
+    /// the QUIC version into account.
+    ///
+    /// QUIC version 2 rotates the numbering: the wire value maps through
+    /// `type.wrapping_sub(version == v2) & 3`, so v2 encodes Initial as 1, 0-RTT
+    /// as 2, Handshake as 3 and Retry as 0. Version 1 and unknown versions use
+    /// the identity mapping.
+    pub fn from_wire(type_bits: u8, version: Version) -> Self {
+        let rotated = type_bits.wrapping_sub((version == Version::Version2) as u8) & 0x3;
+        Self::from(rotated)
+    }
+}
+
 #[derive(Debug)]
 pub struct LongHeader<'a> {
     /// Fixed Bit: The next bit (0x40) of byte 0 is set to 1, unless the packet
@@ -135,6 +446,10 @@ pub struct LongHeader<'a> {
     /// how the rest of the protocol fields are interpreted.
     pub version: u32,
 
+    /// The classified QUIC version, derived from [`version`](Self::version).
+    /// Used to resolve the logical [`ptype`](Self::ptype) across versions.
+    pub version_type: Version,
+
     /// Destination Connection ID Length: The byte following the version contains
     /// the length in bytes of the Destination Connection ID field that follows it.
     /// This length is encoded as an 8-bit unsigned integer. In QUIC version 1,
@@ -168,20 +483,20 @@ pub struct LongHeader<'a> {
 }
 
 impl<'a> LongHeader<'a> {
+    /// Parse a long header from a datagram that is known to hold one.
+    ///
+    /// Callers that may receive either header form or a truncated datagram
+    /// should go through [`Packet::from_slice`], which validates lengths and
+    /// dispatches on the form bit; this method assumes a well-formed long header
+    /// (the short-header and Version Negotiation cases are handled by the
+    /// caller) and is used by the unprotected decode paths and tests.
     pub fn from_slice(buf: &'a [u8]) -> Self {
         let mut pos = 0;
         let first = buf[pos];
-        println!("{:x}", first);
+        debug_assert_eq!(first & 0x80, 0x80, "long header expected");
         pos += 1;
-        let header_form = first & 0x80;
-        if header_form == 0x0 {
-            todo!("Short header");
-        }
 
         let version = u32::from_be_bytes([buf[pos], buf[pos + 1], buf[pos + 2], buf[pos + 3]]);
-        if version == 0 {
-            todo!("Version negotiation packet");
-        }
         pos += 4;
         let dcid_len = buf[pos];
         pos += 1;
@@ -196,7 +511,8 @@ impl<'a> LongHeader<'a> {
         let fixed_bit = (first & 0x40) == 0x40;
         let reserved_bits = first & 0x0c;
         let packet_number_length = (first & 0x03) + 1;
-        let ptype = LongPacketType::from(first & 0x30);
+        let version_type = Version::from(version);
+        let ptype = LongPacketType::from_wire((first & 0x30) >> 4, version_type);
 
         Self {
             fixed_bit,
@@ -204,6 +520,7 @@ impl<'a> LongHeader<'a> {
             reserved_bits,
             packet_number_length,
             version,
+            version_type,
             destination_connection_id_length: dcid_len,
             destination_connection_id: dcid,
             source_connection_id_length: scid_len,
@@ -211,6 +528,70 @@ impl<'a> LongHeader<'a> {
             len: pos,
         }
     }
+
+    /// Parse a long header, removing header protection first.
+    ///
+    /// Unlike [`from_slice`](Self::from_slice), this variant assumes the first
+    /// byte's low bits and the packet number are still masked with header
+    /// protection, as they are on the wire. The version-invariant fields
+    /// (version, connection IDs) are read directly, then the packet-number
+    /// offset is used to sample the ciphertext and derive the mask that unmasks
+    /// the first byte and the packet number; see Section 5.4 of [QUIC-TLS]. The
+    /// unmasked packet number is returned alongside the header in its truncated
+    /// 1-to-4-byte form; it is not reconstructed against a largest-acked value.
+    pub fn from_slice_protected(
+        buf: &'a [u8],
+        hp_key: &HeaderProtectionKey,
+    ) -> Option<(Self, PacketNumber)> {
+        let mut header = Self::from_slice(buf);
+        // The Packet Number is preceded by the Length varint (and, for Initial
+        // packets, the Token-Length varint and token), none of which are header
+        // protected. Walk them to find the true Packet Number offset.
+        let mut pos = header.len;
+        if matches!(header.ptype, LongPacketType::Inital) {
+            let (token_len, n) = read_varint(buf.get(pos..)?)?;
+            pos += n + token_len as usize;
+        }
+        let (_, n) = read_varint(buf.get(pos..)?)?;
+        let pn_offset = pos + n;
+
+        let (first, pn_len, packet_number) =
+            remove_header_protection(buf, pn_offset, hp_key, 0x0f)?;
+
+        header.reserved_bits = first & 0x0c;
+        header.packet_number_length = pn_len;
+        Some((header, packet_number))
+    }
+}
+
+/// Remove header protection from `buf` given the offset of the Packet Number
+/// field, returning the unmasked first byte, the recovered packet-number length
+/// and the recovered packet number. Returns `None` if the datagram is too short
+/// to hold the header-protection sample or the packet number.
+///
+/// `first_byte_mask` selects the bits of byte 0 that are protected: `0x0f` for
+/// long headers and `0x1f` for short headers (which also protects the key-phase
+/// bit). See Section 5.4.1 of [QUIC-TLS].
+fn remove_header_protection(
+    buf: &[u8],
+    pn_offset: usize,
+    hp_key: &HeaderProtectionKey,
+    first_byte_mask: u8,
+) -> Option<(u8, u8, PacketNumber)> {
+    let sample_start = pn_offset + SAMPLE_OFFSET;
+    let sample = buf.get(sample_start..sample_start + SAMPLE_SIZE)?;
+    let mask = hp_key.mask(sample);
+
+    let first = buf[0] ^ (mask[0] & first_byte_mask);
+    let pn_len = (first & 0x03) + 1;
+
+    let mut packet_number: PacketNumber = 0;
+    for i in 0..pn_len as usize {
+        let byte = *buf.get(pn_offset + i)? ^ mask[1 + i];
+        packet_number = (packet_number << 8) | byte as PacketNumber;
+    }
+
+    Some((first, pn_len, packet_number))
 }
 
 #[derive(Debug)]
@@ -256,6 +637,223 @@ pub struct ShortHeader {
     // length of the header, may remove later
 }
 
+impl ShortHeader {
+    /// A zeroed short header, used as a scratch value while the version-specific
+    /// fields are filled in during decode.
+    fn empty() -> Self {
+        Self {
+            fixed_bit: false,
+            spin_bit: false,
+            reserved_bit: [false; 2],
+            key_phase: false,
+            packet_number_length: 0,
+            destination_connection_id: [0u8; 160],
+        }
+    }
+}
+
+/// Decoder for the packet-number length and truncated packet number carried in
+/// a protected header. The largest packet number already acknowledged in the
+/// relevant number space is used to reconstruct the full 62-bit value from the
+/// 1-to-4-byte on-the-wire encoding; see Section 17.1 of [QUIC-TRANSPORT].
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PacketNumberDecoder {
+    pub largest_acked: Option<PacketNumber>,
+}
+
+impl PacketNumberDecoder {
+    /// Reconstruct the full packet number from its truncated form.
+    pub fn decode(&self, truncated: PacketNumber, pn_len: u8) -> PacketNumber {
+        let expected = self.largest_acked.map_or(0, |n| n + 1);
+        let win = 1u64 << (pn_len as u64 * 8);
+        let half = win / 2;
+        let candidate = (expected & !(win - 1)) | truncated;
+        if candidate + half <= expected && candidate + win < (1u64 << 62) {
+            candidate + win
+        } else if candidate > expected + half && candidate >= win {
+            candidate - win
+        } else {
+            candidate
+        }
+    }
+}
+
+/// Read a variable-length integer, returning its value and the number of bytes
+/// it consumed, or `None` on truncation. Thin adapter over
+/// [`crate::varint::VarInt`] for the `Option`-based decode paths here.
+fn read_varint(buf: &[u8]) -> Option<(u64, usize)> {
+    let (v, len) = crate::varint::VarInt::decode(buf).ok()?;
+    Some((v.value(), len))
+}
+
+/// A first-pass decode of the version-invariant parts of a datagram, performed
+/// before any keys are available.
+///
+/// QUIC's invariants ([RFC8999]) let a receiver read the first-byte form bit,
+/// the version, and the connection IDs without knowing the version-specific
+/// field layout. `PartialDecode` parses exactly that much and records a cursor
+/// to the still-protected remainder, so routing (by Destination Connection ID)
+/// can happen before the crypto context is selected. Because a UDP datagram may
+/// coalesce several packets, it also exposes the trailing bytes so the caller
+/// can iterate.
+#[derive(Debug)]
+pub struct PartialDecode<'a> {
+    /// The unmodified first byte (still header-protected).
+    pub first_byte: u8,
+
+    /// `true` for long headers, `false` for short headers.
+    pub long_header: bool,
+
+    /// The QUIC version, or `None` for short headers which carry no version.
+    pub version: Option<u32>,
+
+    /// Whether `version` appears in the caller-supplied supported set.
+    pub version_supported: bool,
+
+    /// Destination Connection ID.
+    pub dcid: &'a [u8],
+
+    /// Source Connection ID, present only for long headers.
+    pub scid: Option<&'a [u8]>,
+
+    /// This packet's bytes, from the first byte up to (but not including) any
+    /// coalesced packet that follows.
+    pub packet: &'a [u8],
+
+    /// Offset within [`packet`](Self::packet) at which the still-protected
+    /// version-specific bytes begin (the byte after the last invariant field).
+    pub header_offset: usize,
+
+    /// Any trailing bytes belonging to coalesced packets, empty when the
+    /// datagram carried a single packet.
+    pub remainder: &'a [u8],
+}
+
+impl<'a> PartialDecode<'a> {
+    /// Parse the version-invariant header of the first packet in `buf`.
+    ///
+    /// `local_cid_len` is the length of connection IDs this endpoint issues; it
+    /// is required for short headers, which do not encode the DCID length on the
+    /// wire. `supported_versions` is consulted only to set
+    /// [`version_supported`](Self::version_supported); unknown versions are
+    /// still parsed so a Version Negotiation response can be formed.
+    ///
+    /// Returns `None` if the datagram is too short to hold the invariant fields.
+    pub fn new(
+        buf: &'a [u8],
+        local_cid_len: usize,
+        supported_versions: &[u32],
+    ) -> Option<Self> {
+        let first = *buf.first()?;
+        let long_header = first & 0x80 != 0;
+
+        if !long_header {
+            // Short header: form bit, then the DCID of the locally advertised
+            // length. The rest of the datagram is this packet; 1-RTT packets
+            // are never coalesced ahead of another packet.
+            let end = 1 + local_cid_len;
+            if buf.len() < end {
+                return None;
+            }
+            return Some(Self {
+                first_byte: first,
+                long_header,
+                version: None,
+                version_supported: false,
+                dcid: &buf[1..end],
+                scid: None,
+                packet: buf,
+                header_offset: end,
+                remainder: &[],
+            });
+        }
+
+        let mut pos = 1;
+        if buf.len() < pos + 4 {
+            return None;
+        }
+        let version = u32::from_be_bytes([buf[pos], buf[pos + 1], buf[pos + 2], buf[pos + 3]]);
+        pos += 4;
+
+        let dcid_len = *buf.get(pos)? as usize;
+        pos += 1;
+        if buf.len() < pos + dcid_len {
+            return None;
+        }
+        let dcid = &buf[pos..pos + dcid_len];
+        pos += dcid_len;
+
+        let scid_len = *buf.get(pos)? as usize;
+        pos += 1;
+        if buf.len() < pos + scid_len {
+            return None;
+        }
+        let scid = &buf[pos..pos + scid_len];
+        pos += scid_len;
+
+        let header_offset = pos;
+        let span = Self::long_packet_span(version, first, &buf[pos..]).map(|n| pos + n);
+        let packet_end = span.unwrap_or(buf.len()).min(buf.len());
+
+        Some(Self {
+            first_byte: first,
+            long_header,
+            version: Some(version),
+            version_supported: supported_versions.contains(&version),
+            dcid,
+            scid: Some(scid),
+            packet: &buf[..packet_end],
+            header_offset,
+            remainder: &buf[packet_end..],
+        })
+    }
+
+    /// Compute the length of a long packet's version-specific region given the
+    /// bytes that follow the invariant header, or `None` when the packet runs to
+    /// the end of the datagram (Retry and Version Negotiation) or is truncated.
+    fn long_packet_span(version: u32, first: u8, rest: &[u8]) -> Option<usize> {
+        // Version Negotiation and Retry (and unparseable versions) extend to the
+        // end of the datagram and cannot be followed by a coalesced packet.
+        if version == 0 {
+            return None;
+        }
+        let ptype = LongPacketType::from_wire((first & 0x30) >> 4, Version::from(version));
+        if matches!(ptype, LongPacketType::Retry) {
+            return None;
+        }
+
+        let mut pos = 0;
+        if matches!(ptype, LongPacketType::Inital) {
+            // Initial: token-length varint + token.
+            let (token_len, n) = read_varint(&rest[pos..])?;
+            pos += n;
+            pos += token_len as usize;
+        }
+        // Initial/0-RTT/Handshake: a Length varint covering the packet number
+        // and payload.
+        let (length, n) = read_varint(rest.get(pos..)?)?;
+        pos += n;
+        Some(pos + length as usize)
+    }
+
+    /// Consume the crypto context and finish decoding into a [`Packet`].
+    ///
+    /// Header protection is removed using `hp_key`, the truncated packet number
+    /// is reconstructed with `pn_decoder`, and the fully decoded packet is
+    /// returned. [`Packet::Inital`] has no packet-number field, so for Initial
+    /// packets the recovered packet number is not returned.
+    /// The remaining coalesced bytes are available separately via
+    /// [`remainder`](Self::remainder). Returns `None` when the protected region
+    /// is too short to sample or the packet is malformed.
+    pub fn finish(
+        self,
+        hp_key: &HeaderProtectionKey,
+        pn_decoder: PacketNumberDecoder,
+    ) -> Option<Packet<'a>> {
+        Packet::decode(self, hp_key, pn_decoder)
+    }
+}
+
 #[derive(Debug)]
 pub enum Packet<'a> {
     Inital {
@@ -275,18 +873,21 @@ pub enum Packet<'a> {
     },
     VersionNegotiaion {
         header: LongHeader<'a>,
-        supported_version: u32,
+
+        /// The list of versions the server supports, parsed to the end of the
+        /// datagram. Each entry is a 32-bit version number.
+        supported_versions: Vec<u32>,
     },
     ZeroRTT {
         header: LongHeader<'a>,
         length: usize,
-        packet_number: u32,
+        packet_number: PacketNumber,
         packet_payload: Vec<u8>,
     },
     Handshake {
         header: LongHeader<'a>,
         length: usize,
-        packet_number: u32,
+        packet_number: PacketNumber,
         packet_payload: Vec<u8>,
     },
     Retry {
@@ -296,6 +897,12 @@ pub enum Packet<'a> {
         /// the client's address.
         retry_token: Vec<u8>,
 
+        /// The received Retry packet bytes up to (but excluding) the integrity
+        /// tag. The tag is computed over these exact bytes, so they are retained
+        /// verbatim rather than re-encoded from the parsed fields, whose
+        /// "Unused" low bits would otherwise be lost.
+        bytes_before_tag: &'a [u8],
+
         /// Retry Integrity Tag: Defined in Section 5.8 ("Retry Packet Integrity")
         /// of [QUIC-TLS].
         retry_integrity_tag: [u8; 16],
@@ -308,16 +915,521 @@ pub enum Packet<'a> {
         /// The length of the Packet Number field is encoded in Packet Number
         /// Length field.
         /// See Section 17.1 for details.
-        packet_number: u32,
+        packet_number: PacketNumber,
 
         /// Packet Payload: 1-RTT packets always include a 1-RTT protected payload.
         packet_payload: Vec<u8>,
     },
 }
 
+/// The version 1 / version 2 limit on connection ID length; longer IDs in a
+/// known-version long header cause the packet to be dropped.
+const MAX_CID_LEN: usize = 20;
+
 impl<'a> Packet<'a> {
-    pub fn from_slice(buf: &'a [u8]) -> Self {
-        let _ = buf;
-        unimplemented!()
+    /// Decode a single packet from `buf`, classifying it by header form and (for
+    /// long headers) packet type.
+    ///
+    /// This is the key-free structural decode: token, length and connection-ID
+    /// fields (which are not header-protected) are read with bounds checks, so
+    /// the UDP receive loop can route and classify a datagram before crypto is
+    /// available. The packet number itself remains header-protected; use
+    /// [`PartialDecode::finish`] once keys are known to recover it.
+    pub fn from_slice(buf: &'a [u8]) -> crate::error::Res<Self> {
+        use crate::error::Error;
+
+        let first = *buf.first().ok_or(Error::UnexpectedEnd)?;
+        if first & 0x80 == 0 {
+            // Short header / 1-RTT. Without the locally advertised connection ID
+            // length the DCID cannot be sliced here, so only the payload is
+            // captured; PartialDecode carries the CID length for full decodes.
+            let mut header = ShortHeader::empty();
+            header.fixed_bit = first & 0x40 != 0;
+            header.spin_bit = first & 0x20 != 0;
+            return Ok(Self::OneRTT {
+                header,
+                packet_number: 0,
+                packet_payload: buf[1..].to_vec(),
+            });
+        }
+
+        if buf.len() < 5 {
+            return Err(Error::UnexpectedEnd);
+        }
+        let version = u32::from_be_bytes([buf[1], buf[2], buf[3], buf[4]]);
+
+        // Read the invariant connection-ID fields with bounds checks before any
+        // version-specific interpretation.
+        let mut pos = 5;
+        let dcid_len = *buf.get(pos).ok_or(Error::UnexpectedEnd)? as usize;
+        pos += 1;
+        let dcid_end = pos + dcid_len;
+        if buf.len() < dcid_end {
+            return Err(Error::UnexpectedEnd);
+        }
+        pos = dcid_end;
+        let scid_len = *buf.get(pos).ok_or(Error::UnexpectedEnd)? as usize;
+        pos += 1;
+        let scid_end = pos + scid_len;
+        if buf.len() < scid_end {
+            return Err(Error::UnexpectedEnd);
+        }
+
+        if version == 0 {
+            // Version Negotiation: the remainder is a list of 32-bit versions.
+            let header = LongHeader::from_slice(buf);
+            let mut supported_versions = Vec::new();
+            let mut vpos = scid_end;
+            while vpos + 4 <= buf.len() {
+                supported_versions.push(u32::from_be_bytes([
+                    buf[vpos],
+                    buf[vpos + 1],
+                    buf[vpos + 2],
+                    buf[vpos + 3],
+                ]));
+                vpos += 4;
+            }
+            return Ok(Self::VersionNegotiaion {
+                header,
+                supported_versions,
+            });
+        }
+
+        // Versions 1 and 2 forbid connection IDs longer than 20 bytes.
+        let version_type = Version::from(version);
+        if matches!(version_type, Version::Version1 | Version::Version2)
+            && (dcid_len > MAX_CID_LEN || scid_len > MAX_CID_LEN)
+        {
+            return Err(Error::InvalidPacket);
+        }
+
+        let header = LongHeader::from_slice(buf);
+        let body = &buf[scid_end..];
+
+        match header.ptype {
+            LongPacketType::Inital => {
+                let (token_length, n) = crate::varint::VarInt::decode(body)?;
+                let token_start = n;
+                let token_end = token_start + token_length.as_usize();
+                let token = body
+                    .get(token_start..token_end)
+                    .ok_or(Error::UnexpectedEnd)?
+                    .to_vec();
+                // The Length varint follows; validate it is present.
+                crate::varint::VarInt::decode(body.get(token_end..).ok_or(Error::UnexpectedEnd)?)?;
+                Ok(Self::Inital {
+                    header,
+                    token_length: token_length.as_usize(),
+                    token,
+                })
+            }
+            LongPacketType::ZeroRTT | LongPacketType::Handshake => {
+                let (length, n) = crate::varint::VarInt::decode(body)?;
+                let pn_len = header.packet_number_length as usize;
+                let pn_start = n;
+                let pn_end = pn_start + pn_len;
+                let pn_bytes = body.get(pn_start..pn_end).ok_or(Error::UnexpectedEnd)?;
+                let mut packet_number: PacketNumber = 0;
+                for &b in pn_bytes {
+                    packet_number = (packet_number << 8) | b as PacketNumber;
+                }
+                let payload_end = n + length.as_usize();
+                let packet_payload = body
+                    .get(pn_end..payload_end)
+                    .ok_or(Error::UnexpectedEnd)?
+                    .to_vec();
+                if matches!(header.ptype, LongPacketType::ZeroRTT) {
+                    Ok(Self::ZeroRTT {
+                        header,
+                        length: length.as_usize(),
+                        packet_number,
+                        packet_payload,
+                    })
+                } else {
+                    Ok(Self::Handshake {
+                        header,
+                        length: length.as_usize(),
+                        packet_number,
+                        packet_payload,
+                    })
+                }
+            }
+            LongPacketType::Retry => {
+                if body.len() < 16 {
+                    return Err(Error::UnexpectedEnd);
+                }
+                let tag_start = body.len() - 16;
+                let mut retry_integrity_tag = [0u8; 16];
+                retry_integrity_tag.copy_from_slice(&body[tag_start..]);
+                Ok(Self::Retry {
+                    header,
+                    retry_token: body[..tag_start].to_vec(),
+                    bytes_before_tag: &buf[..scid_end + tag_start],
+                    retry_integrity_tag,
+                })
+            }
+            LongPacketType::Unknown(_) => Err(Error::InvalidPacket),
+        }
+    }
+
+    /// Key-dependent half of the two-stage decode: given the version-invariant
+    /// [`PartialDecode`], remove header protection and build the typed packet.
+    ///
+    /// Version Negotiation and Retry carry no protected packet number, so they
+    /// are built without touching `hp_key`.
+    fn decode(
+        partial: PartialDecode<'a>,
+        hp_key: &HeaderProtectionKey,
+        pn_decoder: PacketNumberDecoder,
+    ) -> Option<Self> {
+        let buf = partial.packet;
+
+        if !partial.long_header {
+            // 1-RTT: the short header is followed directly by the protected
+            // packet number and payload.
+            let pn_offset = partial.header_offset;
+            if buf.len() < pn_offset + SAMPLE_OFFSET + SAMPLE_SIZE {
+                return None;
+            }
+            let (first, pn_len, truncated) =
+                remove_header_protection(buf, pn_offset, hp_key, 0x1f)?;
+            let packet_number = pn_decoder.decode(truncated, pn_len);
+            let mut header = ShortHeader::empty();
+            header.fixed_bit = first & 0x40 != 0;
+            header.spin_bit = first & 0x20 != 0;
+            header.reserved_bit = [first & 0x10 != 0, first & 0x08 != 0];
+            header.key_phase = first & 0x04 != 0;
+            header.packet_number_length = pn_len;
+            header.destination_connection_id[..partial.dcid.len()]
+                .copy_from_slice(partial.dcid);
+            let payload = buf[pn_offset + pn_len as usize..].to_vec();
+            return Some(Self::OneRTT {
+                header,
+                packet_number,
+                packet_payload: payload,
+            });
+        }
+
+        let mut header = LongHeader::from_slice(buf);
+        let version = partial.version?;
+        if version == 0 {
+            // Version Negotiation: the rest of the datagram is a list of 32-bit
+            // supported versions.
+            let supported_versions = buf[header.len..]
+                .chunks_exact(4)
+                .map(|c| u32::from_be_bytes([c[0], c[1], c[2], c[3]]))
+                .collect();
+            return Some(Self::VersionNegotiaion {
+                header,
+                supported_versions,
+            });
+        }
+        match header.ptype {
+            LongPacketType::Retry => {
+                if buf.len() < header.len + 16 {
+                    return None;
+                }
+                let tag_start = buf.len() - 16;
+                let mut retry_integrity_tag = [0u8; 16];
+                retry_integrity_tag.copy_from_slice(&buf[tag_start..]);
+                let retry_token = buf[header.len..tag_start].to_vec();
+                Some(Self::Retry {
+                    header,
+                    retry_token,
+                    bytes_before_tag: &buf[..tag_start],
+                    retry_integrity_tag,
+                })
+            }
+            LongPacketType::Inital => {
+                let (token_length, n) = read_varint(buf.get(header.len..)?)?;
+                let token_start = header.len + n;
+                let token_end = token_start + token_length as usize;
+                let token = buf.get(token_start..token_end)?.to_vec();
+                let (_, ln) = read_varint(buf.get(token_end..)?)?;
+                let pn_offset = token_end + ln;
+                if buf.len() < pn_offset + SAMPLE_OFFSET + SAMPLE_SIZE {
+                    return None;
+                }
+                let (first, pn_len, truncated) =
+                    remove_header_protection(buf, pn_offset, hp_key, 0x0f)?;
+                header.reserved_bits = first & 0x0c;
+                header.packet_number_length = pn_len;
+                let _ = (version, truncated);
+                Some(Self::Inital {
+                    header,
+                    token_length: token_length as usize,
+                    token,
+                })
+            }
+            LongPacketType::ZeroRTT | LongPacketType::Handshake => {
+                let (length, n) = read_varint(buf.get(header.len..)?)?;
+                let pn_offset = header.len + n;
+                if buf.len() < pn_offset + SAMPLE_OFFSET + SAMPLE_SIZE {
+                    return None;
+                }
+                let (first, pn_len, truncated) =
+                    remove_header_protection(buf, pn_offset, hp_key, 0x0f)?;
+                header.reserved_bits = first & 0x0c;
+                header.packet_number_length = pn_len;
+                let packet_number = pn_decoder.decode(truncated, pn_len);
+                let payload_start = pn_offset + pn_len as usize;
+                let payload_end = pn_offset + length as usize;
+                let packet_payload = buf.get(payload_start..payload_end)?.to_vec();
+                let is_zero_rtt = matches!(header.ptype, LongPacketType::ZeroRTT);
+                if is_zero_rtt {
+                    Some(Self::ZeroRTT {
+                        header,
+                        length: length as usize,
+                        packet_number,
+                        packet_payload,
+                    })
+                } else {
+                    Some(Self::Handshake {
+                        header,
+                        length: length as usize,
+                        packet_number,
+                        packet_payload,
+                    })
+                }
+            }
+            LongPacketType::Unknown(_) => None,
+        }
+    }
+}
+
+/// Fixed Retry integrity key and nonce for a QUIC version; see Section 5.8 of
+/// [QUIC-TLS] (version 1) and Section 3.3.3 of [RFC9369] (version 2).
+struct RetryKey {
+    key: [u8; 16],
+    nonce: [u8; 12],
+}
+
+/// Version 1 Retry integrity key / nonce.
+const RETRY_KEY_V1: RetryKey = RetryKey {
+    key: [
+        0xbe, 0x0c, 0x69, 0x0b, 0x9f, 0x66, 0x57, 0x5a, 0x1d, 0x76, 0x6b, 0x54, 0xe3, 0x68, 0xc8,
+        0x4e,
+    ],
+    nonce: [
+        0x46, 0x15, 0x99, 0xd3, 0x5d, 0x63, 0x2b, 0xf2, 0x23, 0x98, 0x25, 0xbb,
+    ],
+};
+
+/// Version 2 Retry integrity key / nonce.
+const RETRY_KEY_V2: RetryKey = RetryKey {
+    key: [
+        0x8f, 0xb4, 0xb0, 0x1b, 0x56, 0xac, 0x48, 0xe2, 0x60, 0xfb, 0xcb, 0xce, 0xad, 0x7c, 0xcc,
+        0x92,
+    ],
+    nonce: [
+        0xd8, 0x69, 0x69, 0xbc, 0x2d, 0x7c, 0x6d, 0x99, 0x90, 0xef, 0xb0, 0x4a,
+    ],
+};
+
+impl RetryKey {
+    /// Select the Retry key for a version, falling back to the version 1 key for
+    /// unknown versions (matching the historical default).
+    fn for_version(version: Version) -> &'static RetryKey {
+        match version {
+            Version::Version2 => &RETRY_KEY_V2,
+            _ => &RETRY_KEY_V1,
+        }
+    }
+
+    /// Compute the 16-byte Retry integrity tag over a pseudo-packet.
+    fn tag(&self, pseudo_packet: &[u8]) -> [u8; 16] {
+        let cipher = Aes128Gcm::new_from_slice(&self.key).expect("valid key length");
+        let ct = cipher
+            .encrypt(
+                (&self.nonce).into(),
+                Payload {
+                    msg: &[],
+                    aad: pseudo_packet,
+                },
+            )
+            .expect("GCM tag generation never fails");
+        let mut tag = [0u8; 16];
+        tag.copy_from_slice(&ct);
+        tag
+    }
+}
+
+impl<'a> Packet<'a> {
+    /// Verify the integrity tag of a Retry packet against `original_dcid`, the
+    /// Destination Connection ID from the client's Initial that prompted it.
+    ///
+    /// The tag is recomputed over the Retry pseudo-packet (a one-byte ODCID
+    /// length, the original Destination Connection ID, then the received Retry
+    /// bytes up to the tag) using the exact bytes as received, so the arbitrary
+    /// "Unused" low bits of byte 0 are preserved. Returns
+    /// [`Error::RetryIntegrity`](crate::error::Error::RetryIntegrity) on
+    /// mismatch, so a spoofed Retry is dropped. Returns `Ok(())` for non-Retry
+    /// packets.
+    pub fn verify_retry(&self, original_dcid: &[u8]) -> crate::error::Res<()> {
+        let Packet::Retry {
+            header,
+            bytes_before_tag,
+            retry_integrity_tag,
+            ..
+        } = self
+        else {
+            return Ok(());
+        };
+
+        let mut pseudo = Vec::with_capacity(1 + original_dcid.len() + bytes_before_tag.len());
+        pseudo.push(original_dcid.len() as u8);
+        pseudo.extend_from_slice(original_dcid);
+        pseudo.extend_from_slice(bytes_before_tag);
+
+        let expected = RetryKey::for_version(header.version_type).tag(&pseudo);
+        if expected.ct_eq(retry_integrity_tag).into() {
+            Ok(())
+        } else {
+            Err(crate::error::Error::RetryIntegrity)
+        }
+    }
+
+    /// Construct the full on-wire bytes of a Retry packet, appending the
+    /// integrity tag computed from `original_dcid`.
+    pub fn build_retry(
+        version: u32,
+        destination_connection_id: &[u8],
+        source_connection_id: &[u8],
+        retry_token: &[u8],
+        original_dcid: &[u8],
+    ) -> Vec<u8> {
+        let version_type = Version::from(version);
+        let wire_type = 3u8.wrapping_add((version_type == Version::Version2) as u8) & 0x3;
+        let first = 0x80 | 0x40 | (wire_type << 4);
+
+        let mut packet = Vec::new();
+        packet.push(first);
+        packet.extend_from_slice(&version.to_be_bytes());
+        packet.push(destination_connection_id.len() as u8);
+        packet.extend_from_slice(destination_connection_id);
+        packet.push(source_connection_id.len() as u8);
+        packet.extend_from_slice(source_connection_id);
+        packet.extend_from_slice(retry_token);
+
+        let mut pseudo = Vec::new();
+        pseudo.push(original_dcid.len() as u8);
+        pseudo.extend_from_slice(original_dcid);
+        pseudo.extend_from_slice(&packet);
+
+        let tag = RetryKey::for_version(version_type).tag(&pseudo);
+        packet.extend_from_slice(&tag);
+        packet
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // QUIC v1 Retry test vector from RFC 9001 Appendix A.4.
+    const RFC_RETRY: [u8; 36] = [
+        0xff, 0x00, 0x00, 0x00, 0x01, 0x00, 0x08, 0xf0, 0x67, 0xa5, 0x50, 0x2a, 0x42, 0x62, 0xb5,
+        0x74, 0x6f, 0x6b, 0x65, 0x6e, 0x04, 0xa2, 0x65, 0xba, 0x2e, 0xff, 0x4d, 0x82, 0x90, 0x58,
+        0xfb, 0x3f, 0x0f, 0x24, 0x96, 0xba,
+    ];
+    const RFC_ODCID: [u8; 8] = [0x83, 0x94, 0xc8, 0xf0, 0x3e, 0x51, 0x57, 0x08];
+
+    #[test]
+    fn from_slice_dispatches_on_type() {
+        // Initial (v1 type 0) with empty token and zero-length body.
+        let initial = [0xc0, 0, 0, 0, 1, 0x00, 0x00, 0x00, 0x00];
+        assert!(matches!(
+            Packet::from_slice(&initial),
+            Ok(Packet::Inital { token_length: 0, .. })
+        ));
+
+        // Version Negotiation: version 0, then a single supported version.
+        let vneg = [0xc0, 0, 0, 0, 0, 0x00, 0x00, 0, 0, 0, 1];
+        match Packet::from_slice(&vneg) {
+            Ok(Packet::VersionNegotiaion { supported_versions, .. }) => {
+                assert_eq!(supported_versions, vec![1]);
+            }
+            other => panic!("expected version negotiation, got {other:?}"),
+        }
+
+        // A 21-byte DCID (present in full) is dropped under the v1 limit.
+        let mut too_long = vec![0xc0, 0, 0, 0, 1, 21];
+        too_long.extend_from_slice(&[0u8; 21]); // DCID bytes
+        too_long.push(0); // SCID length
+        assert_eq!(
+            Packet::from_slice(&too_long).unwrap_err(),
+            crate::error::Error::InvalidPacket
+        );
+
+        // A truncated datagram errors rather than panicking.
+        assert_eq!(
+            Packet::from_slice(&[0xc0, 0, 0]).unwrap_err(),
+            crate::error::Error::UnexpectedEnd
+        );
+    }
+
+    #[test]
+    fn parse_all_reads_mixed_frames() {
+        // PING, PADDING, MAX_DATA(4), then ACK of 3..=5.
+        let payload = [0x01, 0x00, 0x10, 0x04, 0x02, 0x05, 0x00, 0x00, 0x02];
+        let frames = Frame::parse_all(&payload).unwrap();
+        assert_eq!(frames.len(), 4);
+        assert!(matches!(frames[0], Frame::Ping));
+        assert!(matches!(frames[1], Frame::Padding));
+        assert!(matches!(frames[2], Frame::MaxData { maximum_data: 4 }));
+        match &frames[3] {
+            Frame::Ack { largest_acknowledged, ack_ranges, ecn, .. } => {
+                assert_eq!(*largest_acknowledged, 5);
+                assert_eq!(ack_ranges, &vec![3..=5]);
+                assert!(ecn.is_none());
+            }
+            other => panic!("expected ack, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parse_all_errors_on_truncated_crypto() {
+        // CRYPTO frame claiming 4 bytes of data but carrying none.
+        let payload = [0x06, 0x00, 0x04];
+        assert_eq!(
+            Frame::parse_all(&payload),
+            Err(crate::error::Error::UnexpectedEnd)
+        );
+    }
+
+    #[test]
+    fn verify_retry_accepts_rfc_vector() {
+        let packet = Packet::from_slice(&RFC_RETRY).unwrap();
+        assert!(matches!(packet, Packet::Retry { .. }));
+        assert_eq!(packet.verify_retry(&RFC_ODCID), Ok(()));
+        // A different ODCID must be rejected.
+        assert_eq!(
+            packet.verify_retry(&[0; 8]),
+            Err(crate::error::Error::RetryIntegrity)
+        );
+    }
+
+    #[test]
+    fn build_retry_round_trips() {
+        let scid = [0xde, 0xad, 0xbe, 0xef];
+        let token = b"retry-token";
+        let odcid = [1, 2, 3, 4, 5, 6, 7, 8];
+        let bytes = Packet::build_retry(VERSION_1, &[], &scid, token, &odcid);
+
+        let packet = Packet::from_slice(&bytes).unwrap();
+        match &packet {
+            Packet::Retry { retry_token, .. } => assert_eq!(retry_token, token),
+            other => panic!("expected retry, got {other:?}"),
+        }
+        assert_eq!(packet.verify_retry(&odcid), Ok(()));
+
+        // Flipping a tag byte breaks verification.
+        let mut tampered = bytes.clone();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 0x01;
+        assert_eq!(
+            Packet::from_slice(&tampered).unwrap().verify_retry(&odcid),
+            Err(crate::error::Error::RetryIntegrity)
+        );
     }
 }
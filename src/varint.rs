@@ -1,36 +1,165 @@
-pub enum VarInt {
-    U8(u8),
-    U16(u16),
-    U32(u32),
-    U64(u64),
-    Unknown(u8),
+/// Error returned when a variable-length integer cannot be decoded or encoded.
+#[derive(Debug, PartialEq, Eq)]
+pub enum VarIntError {
+    /// The buffer was shorter than the length advertised by the first byte.
+    UnexpectedEnd,
+
+    /// The value does not fit in the 62-bit space of a QUIC variable-length
+    /// integer.
+    ValueTooLarge,
+}
+
+/// A QUIC variable-length integer.
+///
+/// The on-the-wire encoding stores the length in the two most significant bits
+/// of the first byte (1, 2, 4 or 8 bytes), leaving 6, 14, 30 or 62 bits for the
+/// value. The decoded `value` is kept alongside the number of bytes its
+/// encoding occupied (`len`) so callers can decode-then-advance while walking a
+/// packet; see Section 16 of [QUIC-TRANSPORT].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VarInt {
+    value: u64,
+    len: usize,
 }
 
 impl VarInt {
-    pub fn decode(bytes: &[u8]) -> Self {
-        // The length of variable-length integers is encoded in the
-        // first two bits of the first byte.
-        let v = bytes[0];
-        let prefix = v >> 6;
-        let length = 1 << prefix;
-
-        // Once the length is known, remove these bits and read any
-        // remaining bytes.
-        let v: u8 = v & 0x3f;
-        match length {
-            1 => Self::U8(v),
-            2 => Self::U16(u16::from_be_bytes([v, bytes[1]])),
-            4 => Self::U32(u32::from_be_bytes([v, bytes[1], bytes[2], bytes[3]])),
-            8 => Self::U64(u64::from_be_bytes([
-                v, bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7],
-            ])),
-            num => Self::Unknown(num),
+    /// The largest value a variable-length integer can represent.
+    pub const MAX: u64 = (1 << 62) - 1;
+
+    /// Decode a variable-length integer from the front of `bytes`, returning the
+    /// value together with the number of bytes consumed.
+    ///
+    /// The length is taken from the first two bits of the first byte, which are
+    /// then masked off before the remaining bytes are read big-endian.
+    pub fn decode(bytes: &[u8]) -> Result<(VarInt, usize), VarIntError> {
+        let first = *bytes.first().ok_or(VarIntError::UnexpectedEnd)?;
+        let len = 1usize << (first >> 6);
+        if bytes.len() < len {
+            return Err(VarIntError::UnexpectedEnd);
+        }
+
+        // Remove the length prefix from the first byte, then fold in the rest.
+        let mut value = (first & 0x3f) as u64;
+        for &b in &bytes[1..len] {
+            value = (value << 8) | b as u64;
+        }
+        Ok((VarInt { value, len }, len))
+    }
+
+    /// Encode `value` into the smallest of the 1/2/4/8-byte forms, setting the
+    /// two-bit length prefix, and return the bytes.
+    ///
+    /// Returns [`VarIntError::ValueTooLarge`] if `value` does not fit in 62 bits.
+    pub fn encode(value: u64) -> Result<Vec<u8>, VarIntError> {
+        if value > Self::MAX {
+            return Err(VarIntError::ValueTooLarge);
+        }
+        let out = if value < (1 << 6) {
+            vec![value as u8]
+        } else if value < (1 << 14) {
+            let mut b = (value as u16).to_be_bytes().to_vec();
+            b[0] |= 0x40;
+            b
+        } else if value < (1 << 30) {
+            let mut b = (value as u32).to_be_bytes().to_vec();
+            b[0] |= 0x80;
+            b
+        } else {
+            let mut b = value.to_be_bytes().to_vec();
+            b[0] |= 0xc0;
+            b
+        };
+        Ok(out)
+    }
+
+    /// The decoded value.
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+
+    /// The value as a `usize`, for indexing into a packet buffer.
+    pub fn as_usize(&self) -> usize {
+        self.value as usize
+    }
+
+    /// The number of bytes the encoding occupied.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the encoded length is zero. A decoded `VarInt` is never empty;
+    /// provided to satisfy the usual `len`/`is_empty` pairing.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl TryFrom<u64> for VarInt {
+    type Error = VarIntError;
+
+    fn try_from(value: u64) -> Result<Self, Self::Error> {
+        if value > Self::MAX {
+            return Err(VarIntError::ValueTooLarge);
         }
+        let len = if value < (1 << 6) {
+            1
+        } else if value < (1 << 14) {
+            2
+        } else if value < (1 << 30) {
+            4
+        } else {
+            8
+        };
+        Ok(VarInt { value, len })
+    }
+}
+
+impl From<VarInt> for u64 {
+    fn from(v: VarInt) -> u64 {
+        v.value
+    }
+}
+
+impl From<VarInt> for usize {
+    fn from(v: VarInt) -> usize {
+        v.value as usize
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_boundaries() {
+        for value in [0, 63, 16383, 1073741823, VarInt::MAX] {
+            let encoded = VarInt::encode(value).unwrap();
+            let (decoded, len) = VarInt::decode(&encoded).unwrap();
+            assert_eq!(decoded.value(), value);
+            assert_eq!(len, encoded.len());
+            assert_eq!(VarInt::try_from(value).unwrap().len(), encoded.len());
+        }
+    }
+
+    #[test]
+    fn smallest_form_chosen() {
+        assert_eq!(VarInt::encode(63).unwrap().len(), 1);
+        assert_eq!(VarInt::encode(64).unwrap().len(), 2);
+        assert_eq!(VarInt::encode(16383).unwrap().len(), 2);
+        assert_eq!(VarInt::encode(16384).unwrap().len(), 4);
+        assert_eq!(VarInt::encode(1073741823).unwrap().len(), 4);
+        assert_eq!(VarInt::encode(1073741824).unwrap().len(), 8);
+    }
+
     #[test]
-    fn decode_nums() {}
+    fn rejects_too_large() {
+        assert_eq!(VarInt::encode(1 << 62), Err(VarIntError::ValueTooLarge));
+        assert_eq!(VarInt::try_from(1 << 62), Err(VarIntError::ValueTooLarge));
+    }
+
+    #[test]
+    fn decode_rejects_truncation() {
+        // First byte advertises a 4-byte integer but only two are present.
+        assert_eq!(VarInt::decode(&[0x80, 0x00]), Err(VarIntError::UnexpectedEnd));
+    }
 }